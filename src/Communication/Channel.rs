@@ -0,0 +1,29 @@
+use std::net::UdpSocket;
+use Communication::Checksum::{Checksum, Crc32Ieee};
+
+pub struct Channel {
+    pub write_udp_socket: UdpSocket,
+    pub read_udp_socket: UdpSocket,
+    pub checksum: Box<dyn Checksum>,
+}
+
+impl Channel {
+    pub fn new(write_bind_addr: &str, read_bind_addr: &str) -> Channel {
+        Channel::new_with_checksum(write_bind_addr, read_bind_addr, Box::new(Crc32Ieee))
+    }
+
+    /// Builds a `Channel` that negotiates with a non-default checksum
+    /// algorithm, e.g. `Xor` for low-end devices that can't afford CRC32.
+    pub fn new_with_checksum(
+        write_bind_addr: &str,
+        read_bind_addr: &str,
+        checksum: Box<dyn Checksum>,
+    ) -> Channel {
+        Channel {
+            write_udp_socket: UdpSocket::bind(write_bind_addr)
+                .expect("Could not bind write socket"),
+            read_udp_socket: UdpSocket::bind(read_bind_addr).expect("Could not bind read socket"),
+            checksum,
+        }
+    }
+}
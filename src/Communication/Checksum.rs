@@ -0,0 +1,84 @@
+//! Pluggable packet checksums.
+//!
+//! CRC32 is too expensive for some low-end devices on the bus, so the
+//! algorithm used for a given `Channel` is selectable: a cheap running-XOR
+//! checksum is available alongside the CRC32 default, and the algorithm in
+//! use is carried on the wire so a reply can be validated without the
+//! reader having to guess.
+
+extern crate crc;
+
+use self::crc::crc32;
+
+pub trait Checksum: Send + Sync {
+    /// Number of trailing bytes the checksum occupies on the wire.
+    fn width(&self) -> usize;
+    /// Computes the checksum of `bytes`.
+    fn compute(&self, bytes: &[u8]) -> u64;
+    /// Byte written into the setup broadcast so a receiver can select the
+    /// matching algorithm when validating a reply.
+    fn id(&self) -> u8;
+}
+
+pub struct Crc32Ieee;
+
+impl Checksum for Crc32Ieee {
+    fn width(&self) -> usize {
+        4
+    }
+
+    fn compute(&self, bytes: &[u8]) -> u64 {
+        crc32::checksum_ieee(bytes) as u64
+    }
+
+    fn id(&self) -> u8 {
+        0
+    }
+}
+
+pub struct Xor;
+
+impl Checksum for Xor {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn compute(&self, bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0u8, |acc, &byte| acc ^ byte) as u64
+    }
+
+    fn id(&self) -> u8 {
+        1
+    }
+}
+
+/// Looks up the `Checksum` that matches the negotiation byte a device put
+/// on the wire. Unrecognised ids fall back to CRC32, the default algorithm
+/// a freshly set up device would have been told to use.
+pub fn from_id(id: u8) -> Box<dyn Checksum> {
+    match id {
+        1 => Box::new(Xor),
+        _ => Box::new(Crc32Ieee),
+    }
+}
+
+#[test]
+fn test_crc32_width_and_id() {
+    let checksum = Crc32Ieee;
+    assert_eq!(checksum.width(), 4);
+    assert_eq!(checksum.id(), 0);
+}
+
+#[test]
+fn test_xor_compute() {
+    let checksum = Xor;
+    assert_eq!(checksum.compute(&[0x0F, 0xF0, 0x01]), 0xFE);
+    assert_eq!(checksum.width(), 1);
+}
+
+#[test]
+fn test_from_id_round_trips() {
+    assert_eq!(from_id(0).id(), Crc32Ieee.id());
+    assert_eq!(from_id(1).id(), Xor.id());
+    assert_eq!(from_id(42).id(), Crc32Ieee.id());
+}
@@ -0,0 +1,116 @@
+//! Consistent Overhead Byte Stuffing (COBS) framing.
+//!
+//! Lets `0x00` be used as a reliable frame delimiter on the wire, so a
+//! packet's length no longer has to be recovered from a length byte inside
+//! the payload: the decoder just reads until it sees a `0x00`.
+
+#[derive(Debug, PartialEq)]
+pub enum CobsError {
+    UnexpectedEndOfFrame,
+    ZeroCodeByte,
+}
+
+/// Encodes `data` into a single COBS frame, terminated with a `0x00`
+/// delimiter. `data` may contain `0x00` bytes; they are stuffed away, so
+/// the only `0x00` in the output is the trailing delimiter.
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_index = 0;
+    let mut code = 1u8;
+    encoded.push(0); // placeholder for the first code byte
+
+    for &byte in data {
+        if byte == 0 {
+            encoded[code_index] = code;
+            code_index = encoded.len();
+            encoded.push(0); // placeholder for the next code byte
+            code = 1;
+        } else {
+            encoded.push(byte);
+            code += 1;
+            if code == 0xFF {
+                encoded[code_index] = code;
+                code_index = encoded.len();
+                encoded.push(0); // placeholder for the next code byte
+                code = 1;
+            }
+        }
+    }
+    encoded[code_index] = code;
+    encoded.push(0); // frame delimiter
+    encoded
+}
+
+/// Decodes a single COBS frame (not including the trailing `0x00`
+/// delimiter) back into the original bytes.
+pub fn decode(frame: &[u8]) -> Result<Vec<u8>, CobsError> {
+    let mut decoded = Vec::with_capacity(frame.len());
+    let mut index = 0;
+
+    while index < frame.len() {
+        let code = frame[index] as usize;
+        if code == 0 {
+            return Err(CobsError::ZeroCodeByte);
+        }
+        index += 1;
+
+        let run_end = index + (code - 1);
+        if run_end > frame.len() {
+            return Err(CobsError::UnexpectedEndOfFrame);
+        }
+        decoded.extend_from_slice(&frame[index..run_end]);
+        index = run_end;
+
+        if code != 0xFF && index < frame.len() {
+            decoded.push(0);
+        }
+    }
+    Ok(decoded)
+}
+
+/// Splits a raw socket read on `0x00` delimiters and decodes each COBS
+/// frame found, so datagrams that coalesced multiple frames into one read
+/// are still handled correctly. Frames that fail to decode are dropped.
+pub fn decode_frames(buffer: &[u8]) -> Vec<Vec<u8>> {
+    buffer
+        .split(|&byte| byte == 0)
+        .filter(|frame| !frame.is_empty())
+        .filter_map(|frame| decode(frame).ok())
+        .collect()
+}
+
+#[test]
+fn test_roundtrip_empty_payload() {
+    let encoded = encode(&[]);
+    assert_eq!(encoded, vec![1, 0]);
+    assert_eq!(decode(&encoded[..encoded.len() - 1]), Ok(vec![]));
+}
+
+#[test]
+fn test_roundtrip_with_embedded_zeros() {
+    let data = [165, 9, 0, 1, 0x95, 0x1C, 0x82, 0xCB];
+    let encoded = encode(&data);
+    let frame = &encoded[..encoded.len() - 1];
+    assert_eq!(decode(frame), Ok(data.to_vec()));
+}
+
+#[test]
+fn test_254_byte_boundary() {
+    let data = vec![1u8; 254];
+    let encoded = encode(&data);
+    // no zero in the payload, so the first code byte is the 0xFF marker
+    assert_eq!(encoded[0], 0xFF);
+    let frame = &encoded[..encoded.len() - 1];
+    assert_eq!(decode(frame), Ok(data));
+}
+
+#[test]
+fn test_decode_frames_from_coalesced_buffer() {
+    let first = encode(&[165, 9, 15]);
+    let second = encode(&[165, 9, 16]);
+    let mut buffer = first.clone();
+    buffer.extend_from_slice(&second);
+
+    let frames = decode_frames(&buffer);
+    assert_eq!(frames, vec![vec![165, 9, 15], vec![165, 9, 16]]);
+}
@@ -0,0 +1,3 @@
+pub mod Channel;
+pub mod Checksum;
+pub mod Cobs;
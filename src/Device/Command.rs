@@ -0,0 +1,146 @@
+use std::io;
+use std::io::BufRead;
+use Communication::Channel::Channel;
+use Device::Device;
+use Device::Scpi::{self, ParsedCommand};
+
+#[derive(Debug, PartialEq)]
+pub enum command_error {
+    could_not_read_input,
+    malformed_command,
+    unknown_keyword,
+}
+
+/// Implemented by anything that can produce a `CommandExecution` from an
+/// input source, e.g. the text prompt today, voice input eventually.
+pub trait CommandListen {
+    fn listen(&mut self, com_channel: &Channel) -> Result<CommandExecution, command_error>;
+}
+
+pub struct TextInput {
+    prompt: String,
+}
+
+impl TextInput {
+    pub fn new(prompt: &str) -> TextInput {
+        TextInput {
+            prompt: prompt.to_string(),
+        }
+    }
+}
+
+impl CommandListen for TextInput {
+    fn listen(&mut self, _com_channel: &Channel) -> Result<CommandExecution, command_error> {
+        let stdin = io::stdin();
+        let mut line = String::new();
+        match stdin.lock().read_line(&mut line) {
+            Ok(_) => parse_line(&line),
+            Err(_) => Err(command_error::could_not_read_input),
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Result<CommandExecution, command_error> {
+    let parsed = Scpi::parse(line).map_err(|_| command_error::malformed_command)?;
+    command_execution_from_parsed(parsed)
+}
+
+fn command_execution_from_parsed(parsed: ParsedCommand) -> Result<CommandExecution, command_error> {
+    let device_keyword = parsed.path.get(0).ok_or(command_error::malformed_command)?;
+    if !device_keyword.starts_with("DEVICE") {
+        return Err(command_error::unknown_keyword);
+    }
+    let device_id_suffix = &device_keyword["DEVICE".len()..];
+    let device_id = if device_id_suffix.is_empty() {
+        None
+    } else {
+        Some(
+            device_id_suffix
+                .parse::<u8>()
+                .map_err(|_| command_error::malformed_command)?,
+        )
+    };
+
+    match (parsed.path.get(1).map(String::as_str), parsed.query) {
+        (Some("STATUS"), true) => Ok(CommandExecution::DeviceQuery { device_id }),
+        (Some("POWER"), false) => match parsed.args.get(0).map(String::as_str) {
+            Some("ON") => Ok(CommandExecution::DevicePower {
+                device_id,
+                on: true,
+            }),
+            Some("OFF") => Ok(CommandExecution::DevicePower {
+                device_id,
+                on: false,
+            }),
+            _ => Err(command_error::unknown_keyword),
+        },
+        _ => Err(command_error::unknown_keyword),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CommandExecution {
+    DeviceQuery { device_id: Option<u8> },
+    DevicePower { device_id: Option<u8>, on: bool },
+}
+
+impl CommandExecution {
+    pub fn execute(&self, com_channel: &Channel) {
+        match *self {
+            CommandExecution::DeviceQuery { device_id } => {
+                Device::request_status(com_channel, device_id).ok();
+            }
+            CommandExecution::DevicePower { device_id, on } => {
+                Device::send_power_command(com_channel, device_id, on).ok();
+            }
+        }
+    }
+}
+
+#[test]
+fn test_command_execution_from_status_query() {
+    let parsed = Scpi::parse("DEVICE15:STATUS?").unwrap();
+    assert_eq!(
+        command_execution_from_parsed(parsed),
+        Ok(CommandExecution::DeviceQuery { device_id: Some(15) })
+    );
+}
+
+#[test]
+fn test_command_execution_from_power_command() {
+    let parsed = Scpi::parse("DEVICE:POWER ON").unwrap();
+    assert_eq!(
+        command_execution_from_parsed(parsed),
+        Ok(CommandExecution::DevicePower {
+            device_id: None,
+            on: true,
+        })
+    );
+}
+
+#[test]
+fn test_command_execution_rejects_unknown_keyword() {
+    let parsed = Scpi::parse("LIGHTS:POWER ON").unwrap();
+    assert_eq!(
+        command_execution_from_parsed(parsed),
+        Err(command_error::unknown_keyword)
+    );
+}
+
+#[test]
+fn test_command_execution_rejects_out_of_range_device_id() {
+    let parsed = Scpi::parse("DEVICE999:POWER OFF").unwrap();
+    assert_eq!(
+        command_execution_from_parsed(parsed),
+        Err(command_error::malformed_command)
+    );
+}
+
+#[test]
+fn test_command_execution_rejects_non_numeric_device_id() {
+    let parsed = Scpi::parse("DEVICEX:POWER OFF").unwrap();
+    assert_eq!(
+        command_execution_from_parsed(parsed),
+        Err(command_error::malformed_command)
+    );
+}
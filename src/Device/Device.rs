@@ -1,15 +1,105 @@
-extern crate crc;
 extern crate time;
 
-use self::crc::{crc32, Hasher32};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use Communication::Channel::Channel;
+use Communication::Checksum;
+use Communication::Cobs;
+use Wire;
 
-#[derive(Debug)]
-enum Status {
+#[derive(Debug, PartialEq)]
+pub enum Status {
     on,
     off,
 }
 
+/// Monotonically increasing tag on an outgoing command, echoed back by the
+/// device so a reply can be matched to the request that triggered it.
+pub type RequestId = u8;
+
+/// Distinct sentinel from `Wire::HEADER` (the command/reply envelope) so an
+/// unsolicited status push from a device can't be mistaken for a reply to
+/// something we sent.
+pub const TELEMETRY_HEADER: u8 = 90;
+
+const MAX_SETUP_RETRIES: u8 = 3;
+
+/// `RequestId` of `0` is reserved for `Telemetry` frames a device pushes on
+/// its own initiative, so it can never collide with a query's generated id.
+const UNSOLICITED_REQUEST_ID: RequestId = 0;
+
+static NEXT_QUERY_REQUEST_ID: AtomicU8 = AtomicU8::new(1);
+
+/// `Telemetry` frames seen by `await_telemetry_reply` while it was waiting
+/// on a different reply - unsolicited pushes and replies to other queries
+/// alike - held here so `poll_telemetry` can still surface them instead of
+/// them being read off the socket and dropped.
+static PENDING_UNSOLICITED: Mutex<VecDeque<TelemetryEvent>> = Mutex::new(VecDeque::new());
+
+/// Tags a query with a fresh id so its `Telemetry` reply can be told apart
+/// from unsolicited pushes and from replies to other in-flight queries.
+/// `AtomicU8` wraps on overflow, so `UNSOLICITED_REQUEST_ID` is skipped to
+/// keep a wrapped-around query id from colliding with an unsolicited push.
+fn next_query_request_id() -> RequestId {
+    loop {
+        let id = NEXT_QUERY_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        if id != UNSOLICITED_REQUEST_ID {
+            return id;
+        }
+    }
+}
+
+wire_struct! {
+    pub struct SetupCommand {
+        pub header: u8,
+        pub checksum_id: u8,
+        pub request_id: u8,
+        pub reserved0: u8,
+        pub hour: u8,
+        pub minute: u8,
+        pub second: u8,
+    }
+}
+
+wire_struct! {
+    pub struct DeviceReply {
+        pub header: u8,
+        pub checksum_id: u8,
+        pub device_id: u8,
+        pub request_id: u8,
+        pub ack: u8,
+        pub reserved0: u8,
+        pub reserved1: u8,
+    }
+}
+
+wire_struct! {
+    pub struct Telemetry {
+        pub header: u8,
+        pub checksum_id: u8,
+        pub device_id: u8,
+        /// `UNSOLICITED_REQUEST_ID` for a device-initiated push, or the id
+        /// of the query this is a reply to.
+        pub request_id: u8,
+        pub status: u8,
+    }
+}
+
+wire_struct! {
+    pub struct DeviceCommand {
+        pub header: u8,
+        pub checksum_id: u8,
+        pub device_id: u8,
+        pub request_id: u8,
+        pub query: u8,
+        pub arg: u8,
+        pub reserved0: u8,
+        pub reserved1: u8,
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum device_error {
     could_not_setup_devices,
@@ -18,11 +108,6 @@ pub enum device_error {
     could_not_recieve_device_packet,
 }
 
-#[derive(Debug, PartialEq)]
-enum checksum_error {
-    mismatch,
-}
-
 #[derive(Debug, PartialEq)]
 enum data_recieve_error {
     protocol_error,
@@ -38,16 +123,28 @@ pub struct Device {
     status: Status,
 }
 
+/// A device status carried by a `Telemetry` frame: an unsolicited push
+/// surfaced by `poll_telemetry` for the main loop to report, or the reply
+/// returned by `request_status`.
+#[derive(Debug)]
+pub struct TelemetryEvent {
+    pub device_id: u8,
+    pub status: Status,
+}
+
 pub fn set_up_devices<'a>(com_channel: &'a Channel) -> Result<Vec<Device>, device_error> {
     match com_channel
         .write_udp_socket
         .connect("255.255.255.255:62344")
     {
         Ok(result) => {
-            let current_time = time::now();
-            let set_up_command = set_up_command_broadcast();
+            let request_id: RequestId = 0;
+            let mut pending = HashMap::new();
+            pending.insert(request_id, 1u8);
+
+            let set_up_command = Cobs::encode(&set_up_command_broadcast(com_channel, request_id));
             match com_channel.write_udp_socket.send(&set_up_command) {
-                Ok(_) => retrieve_devices(com_channel),
+                Ok(_) => retrieve_devices(com_channel, pending),
                 Err(_) => Err(device_error::could_not_setup_devices),
             }
         }
@@ -55,39 +152,177 @@ pub fn set_up_devices<'a>(com_channel: &'a Channel) -> Result<Vec<Device>, devic
     }
 }
 
-fn retrieve_devices<'a>(com_channel: &'a Channel) -> Result<Vec<Device>, device_error> {
+/// Collects setup replies until the enumeration deadline passes, retrying
+/// the broadcast while any entry in `pending` is still within its retry
+/// budget. Unlike the old recursive version, a read timeout or a transient
+/// socket error no longer ends enumeration early.
+fn retrieve_devices(
+    com_channel: &Channel,
+    pending: HashMap<RequestId, u8>,
+) -> Result<Vec<Device>, device_error> {
+    Ok(retrieve_devices_until(
+        com_channel,
+        pending,
+        Instant::now() + Duration::from_secs(2),
+    ))
+}
+
+/// Every device on the bus echoes the *same* `request_id` back - the one
+/// carried by the single outgoing broadcast - so replies can't be told
+/// apart by `request_id` the way a setup command could be matched to its
+/// reply one-to-one. Dedupe by `device_id` instead, so multiple devices
+/// answering the same broadcast are all collected rather than the first
+/// reply looking like it satisfied every pending request.
+///
+/// A `request_id` stays acceptable for the whole enumeration window even
+/// after `pending` drops it for having exhausted its retry budget -
+/// otherwise a device that replies late, but still within `deadline`,
+/// would be silently dropped.
+fn retrieve_devices_until(
+    com_channel: &Channel,
+    mut pending: HashMap<RequestId, u8>,
+    deadline: Instant,
+) -> Vec<Device> {
     let mut devices: Vec<Device> = vec![];
+    let mut seen_device_ids: HashSet<u8> = HashSet::new();
+    // `pending` shrinks as entries exhaust their retry budget, but a reply
+    // to a broadcast we sent is valid for the whole enumeration window
+    // regardless of whether we're still retrying it - so acceptance is
+    // gated on every `request_id` ever issued, not just the ones still
+    // pending a retry.
+    let issued_request_ids: HashSet<RequestId> = pending.keys().cloned().collect();
+
+    com_channel
+        .read_udp_socket
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .ok();
 
     let mut buffer = [0; 256];
-    //udp_socket.connect("0.0.0.0:56000").expect("Could not bind to 62344");
-    match com_channel.read_udp_socket.recv_from(&mut buffer) {
-        Ok(success) => match get_device_from_bytes(&buffer) {
-            Ok(device_from_buffer) => {
-                devices.push(device_from_buffer);
-                match retrieve_devices(com_channel) {
-                    Ok(recursive_device) => {
-                        devices.extend(recursive_device);
-                        Ok(devices)
+    while Instant::now() < deadline {
+        if let Ok((bytes_read, _)) = com_channel.read_udp_socket.recv_from(&mut buffer) {
+            for frame in Cobs::decode_frames(&buffer[..bytes_read]) {
+                if let Ok((request_id, device)) = get_device_from_bytes(&frame) {
+                    if issued_request_ids.contains(&request_id)
+                        && seen_device_ids.insert(device.device_id)
+                    {
+                        devices.push(device);
                     }
-                    Err(_) => Ok(devices),
                 }
             }
-            Err(e) => Err(device_error::could_not_unserialize_device_packet),
-        },
-        Err(e) => Err(device_error::could_not_recieve_device_packet),
+        }
+
+        let due_for_retry: Vec<RequestId> = pending
+            .iter()
+            .filter(|&(_, &attempts)| attempts < MAX_SETUP_RETRIES)
+            .map(|(&request_id, _)| request_id)
+            .collect();
+        for request_id in due_for_retry {
+            let command = Cobs::encode(&set_up_command_broadcast(com_channel, request_id));
+            if com_channel.write_udp_socket.send(&command).is_ok() {
+                *pending.get_mut(&request_id).unwrap() += 1;
+            }
+        }
+        pending.retain(|_, &mut attempts| attempts < MAX_SETUP_RETRIES);
+    }
+
+    devices
+}
+
+/// Polls for a single unsolicited `Telemetry` packet without blocking for
+/// long, so the main loop can call this from its periodic health-poll tick.
+/// Frames queued by `await_telemetry_reply` while it was waiting on a
+/// different reply are drained first, so they aren't lost; only once that
+/// queue is empty does this read the socket directly. On a direct read only
+/// a frame tagged `UNSOLICITED_REQUEST_ID` is returned; replies tagged with
+/// a query's `request_id` in the datagram that was read are discarded.
+pub fn poll_telemetry(com_channel: &Channel) -> Option<TelemetryEvent> {
+    if let Some(event) = PENDING_UNSOLICITED.lock().unwrap().pop_front() {
+        return Some(event);
     }
+
+    com_channel
+        .read_udp_socket
+        .set_read_timeout(Some(Duration::from_millis(50)))
+        .ok();
+
+    let mut buffer = [0; 256];
+    let (bytes_read, _) = com_channel.read_udp_socket.recv_from(&mut buffer).ok()?;
+
+    decode_telemetry_frames(&buffer[..bytes_read])
+        .into_iter()
+        .find(|&(request_id, _)| request_id == UNSOLICITED_REQUEST_ID)
+        .map(|(_, event)| event)
 }
 
-fn get_device_from_bytes(buffer: &[u8]) -> Result<Device, data_recieve_error> {
+/// Blocks briefly for the `Telemetry` reply tagged with `request_id`.
+/// Frames seen in the meantime that don't match - unsolicited pushes and
+/// replies to other in-flight queries alike - are queued in
+/// `PENDING_UNSOLICITED` rather than discarded, so `poll_telemetry` can
+/// still surface them afterwards.
+fn await_telemetry_reply(com_channel: &Channel, request_id: RequestId) -> Option<TelemetryEvent> {
+    com_channel
+        .read_udp_socket
+        .set_read_timeout(Some(Duration::from_millis(200)))
+        .ok();
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while Instant::now() < deadline {
+        let mut buffer = [0; 256];
+        if let Ok((bytes_read, _)) = com_channel.read_udp_socket.recv_from(&mut buffer) {
+            let mut reply = None;
+            for (id, event) in decode_telemetry_frames(&buffer[..bytes_read]) {
+                if id == request_id {
+                    reply = Some(event);
+                } else {
+                    PENDING_UNSOLICITED.lock().unwrap().push_back(event);
+                }
+            }
+            if reply.is_some() {
+                return reply;
+            }
+        }
+    }
+    None
+}
+
+fn decode_telemetry_frames(buffer: &[u8]) -> Vec<(RequestId, TelemetryEvent)> {
+    Cobs::decode_frames(buffer)
+        .into_iter()
+        .filter(|frame| frame.first() == Some(&TELEMETRY_HEADER))
+        .filter_map(|frame| {
+            let checksum = Checksum::from_id(*frame.get(1).unwrap_or(&0));
+            Wire::decode_frame::<Telemetry>(&frame, checksum.as_ref()).map(|telemetry| {
+                (
+                    telemetry.request_id,
+                    TelemetryEvent {
+                        device_id: telemetry.device_id,
+                        status: status_from_byte(telemetry.status),
+                    },
+                )
+            })
+        })
+        .collect()
+}
+
+fn status_from_byte(byte: u8) -> Status {
+    if byte == 0 {
+        Status::off
+    } else {
+        Status::on
+    }
+}
+
+fn get_device_from_bytes(buffer: &[u8]) -> Result<(RequestId, Device), data_recieve_error> {
     match buffer.first() {
         Some(first) => {
-            if (*first == 165u8) {
-                match validate_checksum(buffer) {
-                    Ok(validated_buffer) => match create_buffer_from_device(buffer) {
-                        Some(result) => Ok(result),
-                        None => Err(data_recieve_error::possible_corrupted_data),
-                    },
-                    Err(_) => Err(data_recieve_error::mismatch_checksum),
+            if (*first == Wire::HEADER) {
+                let checksum = Checksum::from_id(*buffer.get(1).unwrap_or(&0));
+                match Wire::decode_frame::<DeviceReply>(buffer, checksum.as_ref()) {
+                    Some(reply) => {
+                        let request_id = reply.request_id;
+                        Ok((request_id, device_from_reply(reply)))
+                    }
+                    None => Err(data_recieve_error::mismatch_checksum),
                 }
             } else {
                 Err(data_recieve_error::protocol_error)
@@ -97,83 +332,132 @@ fn get_device_from_bytes(buffer: &[u8]) -> Result<Device, data_recieve_error> {
     }
 }
 
-fn set_up_command_broadcast() -> [u8; 11] {
+fn set_up_command_broadcast(com_channel: &Channel, request_id: RequestId) -> Vec<u8> {
     let current_time = time::now();
-    let command_without_checksum = [
-        165,
-        5,
-        0,
-        0,
-        current_time.tm_hour as u8,
-        current_time.tm_min as u8,
-        current_time.tm_sec as u8,
-    ];
-    let checksum = get_checksum(&command_without_checksum);
-    [
-        165,
-        9,
-        0,
-        0,
-        current_time.tm_hour as u8,
-        current_time.tm_min as u8,
-        current_time.tm_sec as u8,
-        (checksum >> 24) as u8,
-        (checksum >> 16) as u8,
-        (checksum >> 8) as u8,
-        (checksum | 0x00ff) as u8,
-    ]
-}
-
-fn get_checksum(bytes: &[u8]) -> u32 {
-    crc32::checksum_ieee(bytes)
-}
-
-fn validate_checksum(buffer: &[u8]) -> Result<(), checksum_error> {
-    if (buffer.len() > 4) {
-        let buffer_without_trailing_zeros = buffer.split_at((buffer[1] + 2) as usize).0;
-        let buffer_without_checksum =
-            buffer_without_trailing_zeros.split_at(buffer_without_trailing_zeros.len() - 4);
-        let calculated_read_buffer_checksum = get_checksum(buffer_without_checksum.0);
-        let checksum = ((buffer_without_checksum.1[0] as u32) << 24)
-            | ((buffer_without_checksum.1[1] as u32) << 16)
-            | ((buffer_without_checksum.1[2] as u32) << 8)
-            | ((buffer_without_checksum.1[3] as u32));
-
-        if (checksum == calculated_read_buffer_checksum) {
-            Ok(())
-        } else {
-            Err(checksum_error::mismatch)
-        }
-    } else {
-        Err(checksum_error::mismatch)
+    let checksum = com_channel.checksum.as_ref();
+    let command = SetupCommand {
+        header: Wire::HEADER,
+        checksum_id: checksum.id(),
+        request_id,
+        reserved0: 0,
+        hour: current_time.tm_hour as u8,
+        minute: current_time.tm_min as u8,
+        second: current_time.tm_sec as u8,
+    };
+    Wire::encode_frame(&command, checksum)
+}
+
+/// Sends a `DEVICE<id>:POWER ON|OFF`-style command to the bus. `device_id`
+/// of `None` broadcasts to every device. No reply is expected, so this
+/// isn't tagged with a query `request_id`.
+pub fn send_power_command(
+    com_channel: &Channel,
+    device_id: Option<u8>,
+    on: bool,
+) -> Result<(), device_error> {
+    send_device_command(com_channel, device_id, UNSOLICITED_REQUEST_ID, false, on as u8)
+}
+
+/// Sends a `DEVICE<id>:STATUS?`-style query to the bus, tagged with a fresh
+/// `request_id`, and blocks briefly for the `Telemetry` reply that echoes
+/// it back - rather than leaving correlation to whichever `poll_telemetry`
+/// tick happens to see a push afterwards.
+pub fn request_status(
+    com_channel: &Channel,
+    device_id: Option<u8>,
+) -> Result<TelemetryEvent, device_error> {
+    let request_id = next_query_request_id();
+    send_device_command(com_channel, device_id, request_id, true, 0)?;
+    await_telemetry_reply(com_channel, request_id).ok_or(device_error::could_not_recieve_device_packet)
+}
+
+fn send_device_command(
+    com_channel: &Channel,
+    device_id: Option<u8>,
+    request_id: RequestId,
+    query: bool,
+    arg: u8,
+) -> Result<(), device_error> {
+    let checksum = com_channel.checksum.as_ref();
+    let command = DeviceCommand {
+        header: Wire::HEADER,
+        checksum_id: checksum.id(),
+        device_id: device_id.unwrap_or(0),
+        request_id,
+        query: query as u8,
+        arg,
+        reserved0: 0,
+        reserved1: 0,
+    };
+    let framed = Cobs::encode(&Wire::encode_frame(&command, checksum));
+    match com_channel.write_udp_socket.send(&framed) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(device_error::could_not_send_setup_command),
     }
 }
 
-fn create_buffer_from_device(buffer: &[u8]) -> Option<Device> {
-    if (buffer.len() > 2) {
-        Some(Device {
-            device_id: buffer[2],
-            status: Status::on,
-        })
-    } else {
-        None
+fn device_from_reply(reply: DeviceReply) -> Device {
+    Device {
+        device_id: reply.device_id,
+        status: Status::on,
+    }
+}
+
+#[test]
+fn test_next_query_request_id_never_collides_with_unsolicited_on_wraparound() {
+    // `AtomicU8` wraps past 255 back to 0, which is `UNSOLICITED_REQUEST_ID` -
+    // calling enough times to wrap at least once should never hand that
+    // value out as a query id.
+    for _ in 0..300 {
+        assert_ne!(next_query_request_id(), UNSOLICITED_REQUEST_ID);
     }
 }
 
 #[test]
-fn test_validate_checksum() {
+fn test_decode_frame_round_trips_device_reply() {
+    let crc32 = Checksum::Crc32Ieee;
+    let reply = DeviceReply {
+        header: Wire::HEADER,
+        checksum_id: 0,
+        device_id: 15,
+        request_id: 3,
+        ack: 1,
+        reserved0: 0,
+        reserved1: 0,
+    };
+    let framed = Wire::encode_frame(&reply, &crc32);
     assert_eq!(
-        validate_checksum(&[165, 9, 15, 1, 0, 0, 0, 0x95, 0x1c, 0x82, 0xcb]),
-        Ok(())
+        Wire::decode_frame::<DeviceReply>(&framed, &crc32),
+        Some(reply)
     );
+}
+
+#[test]
+fn test_decode_frame_rejects_mismatched_checksum() {
+    let crc32 = Checksum::Crc32Ieee;
     assert_eq!(
-        validate_checksum(&[165, 9, 15, 1, 0, 0, 0, 0x95, 0x1c, 0x82, 0xcb, 0, 0, 0, 0, 23]),
-        Ok(())
+        Wire::decode_frame::<DeviceReply>(&[165, 9, 15, 1, 0, 0, 0, 0, 0, 0, 0], &crc32),
+        None
     );
+}
+
+#[test]
+fn test_decode_frame_with_xor_algorithm() {
+    let xor = Checksum::Xor;
+    let reply = DeviceReply {
+        header: Wire::HEADER,
+        checksum_id: 1,
+        device_id: 15,
+        request_id: 3,
+        ack: 1,
+        reserved0: 0,
+        reserved1: 0,
+    };
+    let framed = Wire::encode_frame(&reply, &xor);
+    assert_eq!(framed.len(), 8); // 7 body bytes + 1-byte xor checksum
     assert_eq!(
-        validate_checksum(&[165, 9, 19, 1, 0, 0, 0, 0x95, 0x1C, 0x82, 0xCB])
-            .expect_err("Matching checksum"),
-        checksum_error::mismatch
+        Wire::decode_frame::<DeviceReply>(&framed, &xor),
+        Some(reply)
     );
 }
 
@@ -193,10 +477,201 @@ fn test_get_device_from_bytes() {
             .expect_err("expected corrupted data"),
         data_recieve_error::mismatch_checksum
     );
-    assert_eq!(
-        get_device_from_bytes(&[165, 9, 15, 1, 0, 0, 0, 0x95, 0x1C, 0x82, 0xCB])
-            .expect("expected device")
-            .device_id,
-        15
+    let (request_id, device) = get_device_from_bytes(&[165, 9, 15, 1, 0, 0, 0, 0x95, 0x1C, 0x82, 0xCB])
+        .expect("expected device");
+    assert_eq!(request_id, 1);
+    assert_eq!(device.device_id, 15);
+}
+
+#[test]
+fn test_retrieve_devices_collects_every_device_replying_to_one_broadcast() {
+    use std::net::UdpSocket;
+
+    let com_channel = Channel::new("127.0.0.1:0", "127.0.0.1:0");
+    let read_addr = com_channel.read_udp_socket.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let crc32 = Checksum::Crc32Ieee;
+    let request_id: RequestId = 0;
+    let mut pending = HashMap::new();
+    pending.insert(request_id, 1u8);
+
+    // Every device on the bus echoes the same broadcast request_id back -
+    // there's only one outgoing setup command, not one per device.
+    let mut buffer = Vec::new();
+    for device_id in &[5u8, 9u8] {
+        let reply = DeviceReply {
+            header: Wire::HEADER,
+            checksum_id: 0,
+            device_id: *device_id,
+            request_id,
+            ack: 1,
+            reserved0: 0,
+            reserved1: 0,
+        };
+        buffer.extend(Cobs::encode(&Wire::encode_frame(&reply, &crc32)));
+    }
+    sender.send_to(&buffer, read_addr).unwrap();
+
+    let devices = retrieve_devices_until(
+        &com_channel,
+        pending,
+        Instant::now() + Duration::from_millis(300),
+    );
+    let mut device_ids: Vec<u8> = devices.iter().map(|device| device.device_id).collect();
+    device_ids.sort();
+    assert_eq!(device_ids, vec![5, 9]);
+}
+
+#[test]
+fn test_retrieve_devices_until_accepts_a_late_reply_after_retries_are_exhausted() {
+    use std::net::UdpSocket;
+    use std::thread;
+
+    let com_channel = Channel::new("127.0.0.1:0", "127.0.0.1:0");
+    let read_addr = com_channel.read_udp_socket.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    // Retry broadcasts only count against the budget once `send` succeeds,
+    // which an unconnected socket never does.
+    com_channel
+        .write_udp_socket
+        .connect(sender.local_addr().unwrap())
+        .unwrap();
+
+    let crc32 = Checksum::Crc32Ieee;
+    let request_id: RequestId = 0;
+    let mut pending = HashMap::new();
+    pending.insert(request_id, 1u8);
+
+    // Sent well after `pending`'s retry budget (3 attempts, 200ms recv
+    // timeout each) has been exhausted and the entry dropped from
+    // `pending`, but still comfortably inside the enumeration deadline.
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(500));
+        let reply = DeviceReply {
+            header: Wire::HEADER,
+            checksum_id: 0,
+            device_id: 5,
+            request_id,
+            ack: 1,
+            reserved0: 0,
+            reserved1: 0,
+        };
+        sender
+            .send_to(&Cobs::encode(&Wire::encode_frame(&reply, &crc32)), read_addr)
+            .unwrap();
+    });
+
+    let devices = retrieve_devices_until(
+        &com_channel,
+        pending,
+        Instant::now() + Duration::from_millis(900),
     );
+    let device_ids: Vec<u8> = devices.iter().map(|device| device.device_id).collect();
+    assert_eq!(device_ids, vec![5]);
+}
+
+#[test]
+fn test_poll_telemetry_ignores_non_telemetry_frames() {
+    use std::net::UdpSocket;
+
+    let com_channel = Channel::new("127.0.0.1:0", "127.0.0.1:0");
+    let read_addr = com_channel.read_udp_socket.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let crc32 = Checksum::Crc32Ieee;
+    let reply = DeviceReply {
+        header: Wire::HEADER,
+        checksum_id: 0,
+        device_id: 7,
+        request_id: 3,
+        ack: 1,
+        reserved0: 0,
+        reserved1: 0,
+    };
+    let telemetry = Telemetry {
+        header: TELEMETRY_HEADER,
+        checksum_id: 0,
+        device_id: 15,
+        request_id: UNSOLICITED_REQUEST_ID,
+        status: 1,
+    };
+
+    let mut buffer = Cobs::encode(&Wire::encode_frame(&reply, &crc32));
+    buffer.extend(Cobs::encode(&Wire::encode_frame(&telemetry, &crc32)));
+    sender.send_to(&buffer, read_addr).unwrap();
+
+    let event = poll_telemetry(&com_channel).expect("expected the telemetry event, not the reply frame");
+    assert_eq!(event.device_id, 15);
+    assert_eq!(event.status, Status::on);
+}
+
+#[test]
+fn test_await_telemetry_reply_queues_unmatched_frames_instead_of_dropping_them() {
+    use std::net::UdpSocket;
+
+    let com_channel = Channel::new("127.0.0.1:0", "127.0.0.1:0");
+    let read_addr = com_channel.read_udp_socket.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let crc32 = Checksum::Crc32Ieee;
+    // An unsolicited push and a reply to a different in-flight query, both
+    // seen while awaiting the reply to request_id 9.
+    let unsolicited = Telemetry {
+        header: TELEMETRY_HEADER,
+        checksum_id: 0,
+        device_id: 22,
+        request_id: UNSOLICITED_REQUEST_ID,
+        status: 1,
+    };
+    let other_query_reply = Telemetry {
+        header: TELEMETRY_HEADER,
+        checksum_id: 0,
+        device_id: 23,
+        request_id: 4,
+        status: 1,
+    };
+    let awaited_reply = Telemetry {
+        header: TELEMETRY_HEADER,
+        checksum_id: 0,
+        device_id: 7,
+        request_id: 9,
+        status: 0,
+    };
+
+    let mut buffer = Cobs::encode(&Wire::encode_frame(&unsolicited, &crc32));
+    buffer.extend(Cobs::encode(&Wire::encode_frame(&other_query_reply, &crc32)));
+    buffer.extend(Cobs::encode(&Wire::encode_frame(&awaited_reply, &crc32)));
+    sender.send_to(&buffer, read_addr).unwrap();
+
+    let event = await_telemetry_reply(&com_channel, 9).expect("expected the matching reply");
+    assert_eq!(event.device_id, 7);
+
+    let mut queued = PENDING_UNSOLICITED.lock().unwrap();
+    let mut queued_device_ids: Vec<u8> = queued.drain(..).map(|event| event.device_id).collect();
+    queued_device_ids.sort();
+    assert_eq!(queued_device_ids, vec![22, 23]);
+}
+
+#[test]
+fn test_poll_telemetry_does_not_surface_query_replies() {
+    use std::net::UdpSocket;
+
+    let com_channel = Channel::new("127.0.0.1:0", "127.0.0.1:0");
+    let read_addr = com_channel.read_udp_socket.local_addr().unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+
+    let crc32 = Checksum::Crc32Ieee;
+    let reply_to_query = Telemetry {
+        header: TELEMETRY_HEADER,
+        checksum_id: 0,
+        device_id: 15,
+        request_id: 5,
+        status: 1,
+    };
+    sender
+        .send_to(&Cobs::encode(&Wire::encode_frame(&reply_to_query, &crc32)), read_addr)
+        .unwrap();
+
+    assert!(poll_telemetry(&com_channel).is_none());
 }
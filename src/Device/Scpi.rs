@@ -0,0 +1,77 @@
+//! A small SCPI-inspired grammar for the text command line: hierarchical
+//! colon-separated keywords (`DEVICE15:POWER ON`), a trailing `?` marking a
+//! query (`DEVICE:STATUS?`), and whitespace-separated arguments.
+
+#[derive(Debug, PartialEq)]
+pub struct ParsedCommand {
+    pub path: Vec<String>,
+    pub query: bool,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    empty_input,
+    malformed_keyword,
+}
+
+pub fn parse(line: &str) -> Result<ParsedCommand, ParseError> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Err(ParseError::empty_input);
+    }
+
+    let mut words = line.splitn(2, char::is_whitespace);
+    let keyword = words.next().unwrap();
+    let args = words
+        .next()
+        .map(|rest| {
+            rest.split_whitespace()
+                .map(|arg| arg.to_string())
+                .collect()
+        })
+        .unwrap_or_else(Vec::new);
+
+    let query = keyword.ends_with('?');
+    let keyword = if query {
+        &keyword[..keyword.len() - 1]
+    } else {
+        keyword
+    };
+
+    if keyword.is_empty() || keyword.split(':').any(|segment| segment.is_empty()) {
+        return Err(ParseError::malformed_keyword);
+    }
+
+    let path = keyword.split(':').map(|segment| segment.to_string()).collect();
+
+    Ok(ParsedCommand { path, query, args })
+}
+
+#[test]
+fn test_parse_query() {
+    let parsed = parse("DEVICE:STATUS?").unwrap();
+    assert_eq!(parsed.path, vec!["DEVICE", "STATUS"]);
+    assert!(parsed.query);
+    assert!(parsed.args.is_empty());
+}
+
+#[test]
+fn test_parse_set_with_args() {
+    let parsed = parse("DEVICE15:POWER ON").unwrap();
+    assert_eq!(parsed.path, vec!["DEVICE15", "POWER"]);
+    assert!(!parsed.query);
+    assert_eq!(parsed.args, vec!["ON"]);
+}
+
+#[test]
+fn test_parse_rejects_empty_input() {
+    assert_eq!(parse(""), Err(ParseError::empty_input));
+    assert_eq!(parse("   "), Err(ParseError::empty_input));
+}
+
+#[test]
+fn test_parse_rejects_malformed_keyword() {
+    assert_eq!(parse("DEVICE::POWER ON"), Err(ParseError::malformed_keyword));
+    assert_eq!(parse(":STATUS?"), Err(ParseError::malformed_keyword));
+}
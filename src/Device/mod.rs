@@ -0,0 +1,3 @@
+pub mod Command;
+pub mod Device;
+pub mod Scpi;
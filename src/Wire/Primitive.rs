@@ -0,0 +1,90 @@
+//! Big-endian (de)serialization for the integer widths a device packet
+//! field can have. `wire_struct!` calls these through the `WireValue`
+//! trait so it never has to special-case a field's width itself.
+
+pub trait WireValue: Sized {
+    /// Writes `self` as big-endian bytes at the front of `buffer`,
+    /// returning the number of bytes written.
+    fn write_be(&self, buffer: &mut [u8]) -> usize;
+
+    /// Reads a big-endian value from the front of `buffer`, returning the
+    /// value and the number of bytes consumed, or `None` if `buffer` is
+    /// too short.
+    fn read_be(buffer: &[u8]) -> Option<(Self, usize)>;
+}
+
+macro_rules! impl_wire_value_for_uint {
+    ($ty:ty, $width:expr) => {
+        impl WireValue for $ty {
+            fn write_be(&self, buffer: &mut [u8]) -> usize {
+                let bytes = self.to_be_bytes_compat();
+                buffer[..$width].copy_from_slice(&bytes);
+                $width
+            }
+
+            fn read_be(buffer: &[u8]) -> Option<(Self, usize)> {
+                if buffer.len() < $width {
+                    return None;
+                }
+                // Accumulate in a u64 so the shift is always in range,
+                // then narrow to the target width.
+                let mut accumulator: u64 = 0;
+                for &byte in &buffer[..$width] {
+                    accumulator = (accumulator << 8) | byte as u64;
+                }
+                Some((accumulator as $ty, $width))
+            }
+        }
+    };
+}
+
+trait ToBeBytesCompat {
+    fn to_be_bytes_compat(&self) -> Vec<u8>;
+}
+
+impl ToBeBytesCompat for u8 {
+    fn to_be_bytes_compat(&self) -> Vec<u8> {
+        vec![*self]
+    }
+}
+
+impl ToBeBytesCompat for u16 {
+    fn to_be_bytes_compat(&self) -> Vec<u8> {
+        vec![(*self >> 8) as u8, *self as u8]
+    }
+}
+
+impl ToBeBytesCompat for u32 {
+    fn to_be_bytes_compat(&self) -> Vec<u8> {
+        vec![
+            (*self >> 24) as u8,
+            (*self >> 16) as u8,
+            (*self >> 8) as u8,
+            *self as u8,
+        ]
+    }
+}
+
+impl_wire_value_for_uint!(u8, 1);
+impl_wire_value_for_uint!(u16, 2);
+impl_wire_value_for_uint!(u32, 4);
+
+#[test]
+fn test_u8_round_trip() {
+    let mut buffer = [0u8; 1];
+    assert_eq!(42u8.write_be(&mut buffer), 1);
+    assert_eq!(u8::read_be(&buffer), Some((42u8, 1)));
+}
+
+#[test]
+fn test_u16_round_trip_is_big_endian() {
+    let mut buffer = [0u8; 2];
+    assert_eq!(0x1234u16.write_be(&mut buffer), 2);
+    assert_eq!(buffer, [0x12, 0x34]);
+    assert_eq!(u16::read_be(&buffer), Some((0x1234u16, 2)));
+}
+
+#[test]
+fn test_read_be_too_short() {
+    assert_eq!(u32::read_be(&[1, 2]), None);
+}
@@ -0,0 +1,107 @@
+//! Wire-format (de)serialization for device packets.
+//!
+//! `ToWire`/`FromWire` replace the byte-by-byte packing that used to be
+//! duplicated between `set_up_command_broadcast` and
+//! `create_buffer_from_device`: a struct's fields are encoded in
+//! declaration order as big-endian integers, so the wire layout lives in
+//! one place instead of two. `wire_struct!` is this crate's stand-in for a
+//! derive macro (there's no proc-macro crate set up in this workspace
+//! yet) - it generates the struct itself plus its `ToWire`/`FromWire`
+//! impls from a field list.
+
+pub mod Primitive;
+
+pub use self::Primitive::WireValue;
+use Communication::Checksum::Checksum;
+
+/// The sentinel byte that command and reply packets start with.
+/// `Telemetry` pushes start with `Device::TELEMETRY_HEADER` instead.
+pub const HEADER: u8 = 165;
+
+pub trait ToWire {
+    /// Encodes `self` into the front of `buffer`, returning the number of
+    /// bytes written.
+    fn to_wire(&self, buffer: &mut [u8]) -> usize;
+}
+
+pub trait FromWire: Sized {
+    /// Decodes a value from the front of `buffer`, returning the value and
+    /// the number of bytes consumed, or `None` if `buffer` is too short.
+    fn from_wire(buffer: &[u8]) -> Option<(Self, usize)>;
+}
+
+/// Encodes `body`'s wire representation followed by a trailing checksum
+/// computed over those bytes with `checksum`. The header byte is the
+/// body's own first field; no length field is written, since COBS framing
+/// marks where a packet ends.
+pub fn encode_frame<T: ToWire>(body: &T, checksum: &dyn Checksum) -> Vec<u8> {
+    let mut buffer = [0u8; 64];
+    let body_len = body.to_wire(&mut buffer);
+    let mut framed = buffer[..body_len].to_vec();
+
+    let checksum_value = checksum.compute(&framed);
+    for shift in (0..checksum.width()).rev() {
+        framed.push((checksum_value >> (8 * shift)) as u8);
+    }
+    framed
+}
+
+/// Reverses `encode_frame`: validates the trailing checksum, then decodes
+/// the body that precedes it.
+pub fn decode_frame<T: FromWire>(buffer: &[u8], checksum: &dyn Checksum) -> Option<T> {
+    let width = checksum.width();
+    if buffer.len() <= width {
+        return None;
+    }
+
+    let (body, checksum_bytes) = buffer.split_at(buffer.len() - width);
+    let calculated_checksum = checksum.compute(body);
+    let read_checksum = checksum_bytes
+        .iter()
+        .fold(0u64, |acc, &byte| (acc << 8) | byte as u64);
+
+    if calculated_checksum != read_checksum {
+        return None;
+    }
+
+    T::from_wire(body).map(|(value, _consumed)| value)
+}
+
+/// Generates a plain struct plus `ToWire`/`FromWire` impls that encode its
+/// fields, in declaration order, as big-endian wire values.
+#[macro_export]
+macro_rules! wire_struct {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $( $(#[$field_meta:meta])* pub $field:ident : $ty:ty ),* $(,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, PartialEq)]
+        pub struct $name {
+            $( $(#[$field_meta])* pub $field: $ty ),*
+        }
+
+        impl $crate::Wire::ToWire for $name {
+            fn to_wire(&self, buffer: &mut [u8]) -> usize {
+                let mut offset = 0;
+                $(
+                    offset += $crate::Wire::WireValue::write_be(&self.$field, &mut buffer[offset..]);
+                )*
+                offset
+            }
+        }
+
+        impl $crate::Wire::FromWire for $name {
+            fn from_wire(buffer: &[u8]) -> Option<(Self, usize)> {
+                let mut offset = 0;
+                $(
+                    let ($field, consumed) = $crate::Wire::WireValue::read_be(&buffer[offset..])?;
+                    offset += consumed;
+                )*
+                Some(($name { $($field),* }, offset))
+            }
+        }
+    };
+}
@@ -0,0 +1,5 @@
+#[macro_use]
+pub mod Wire;
+
+pub mod Communication;
+pub mod Device;
@@ -1,16 +1,17 @@
+extern crate crossbeam_channel;
 extern crate Jarvis;
 extern crate num_cpus;
 extern crate termcolor;
 
+use crossbeam_channel::{select, tick, unbounded};
 use std::io::Write;
-use std::sync::{
-    mpsc, mpsc::{Receiver, Sender}, Arc, Mutex,
-};
+use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 use Jarvis::Communication::Channel;
 use Jarvis::Device::Command;
-use Jarvis::Device::Command::{CommandExecution, CommandListen};
+use Jarvis::Device::Command::CommandListen;
 use Jarvis::Device::Device;
 
 enum message_level {
@@ -20,11 +21,6 @@ enum message_level {
     success,
 }
 
-enum execution_order {
-    sync,
-    async,
-}
-
 fn post_message(message: &str, level: message_level) {
     let mut stdout = StandardStream::stdout(ColorChoice::Always);
     let log_message = match level {
@@ -40,61 +36,65 @@ fn listen_to_commands(com_channel: Channel::Channel) {
     post_message("ENTER OR VOICE COMMAND", message_level::info);
     std::io::stdout().flush();
 
-    //use tuple of (io,execution_order) to determine execution of application
-    let channel = mpsc::channel();
-    let io_execution = vec![(Command::TextInput::new(""), execution_order::sync)];
     let thread_data = Arc::new(com_channel);
-    let th = thread_data.clone();
-    let send_clone_channel = channel.0.clone();
-    let results = io_execution
-        .into_iter()
-        .map(move |execution| match execution.1 {
-            execution_order::async => Some(thread::spawn(move || -> () {
-                loop {
-                    let mut text_io = Command::TextInput::new("");
-                    let exec = text_io.listen(&th);
-                    match exec {
-                        Ok(command) => match send_clone_channel.send(command) {
-                            Ok(com) => {}
-                            Err(_) => {
-                                post_message("CHANNEL COULD NOT BE SEND", message_level::error);
-                            }
-                        },
-                        Err(_) => {
-                            post_message("COULD NOT EXECUTE COMMAND", message_level::error);
+
+    // voice input will join this vector as another source once it lands.
+    let io_sources = vec![Command::TextInput::new("")];
+
+    let (command_sender, command_receiver) = unbounded();
+
+    for io in io_sources {
+        // `CommandListen::listen` blocks until input arrives, with no
+        // cancellation or timeout hook, so a `select!` arm that called it
+        // directly would stall every other arm for as long as it blocked.
+        // The only sound way to multiplex it is to run it on its own
+        // thread and forward the result.
+        let th = thread_data.clone();
+        let sender = command_sender.clone();
+        thread::spawn(move || -> () {
+            let mut io = io;
+            loop {
+                match io.listen(&th) {
+                    Ok(command) => {
+                        if sender.send(command).is_err() {
+                            post_message("CHANNEL COULD NOT BE SEND", message_level::error);
+                            break;
                         }
                     }
+                    Err(error) => {
+                        post_message(
+                            &format!("COULD NOT EXECUTE COMMAND: {:?}", error),
+                            message_level::error,
+                        );
+                    }
                 }
-            })),
-            _ => None,
-        })
-        .collect::<Vec<_>>();
+            }
+        });
+    }
+    drop(command_sender);
 
-    let tcount = thread_data.clone();
-    let loop_result = thread::spawn(move || -> () {
-        let thr = tcount.clone();
-        loop {
-            match channel.1.recv() {
-                Ok(exec) => {
-                    exec.execute(&thr);
-                }
+    let health_tick = tick(Duration::from_secs(30));
+
+    loop {
+        select! {
+            recv(command_receiver) -> exec => match exec {
+                Ok(command) => command.execute(&thread_data),
                 Err(_) => {
                     post_message("COULD NOT RECIEVE COMMAND", message_level::error);
+                    return;
                 }
-            }
+            },
+            recv(health_tick) -> _ => {
+                match Device::poll_telemetry(&thread_data) {
+                    Some(event) => post_message(
+                        &format!("Device {} is now {:?}", event.device_id, event.status),
+                        message_level::info,
+                    ),
+                    None => post_message("ENTER OR VOICE COMMAND", message_level::info),
+                }
+            },
         }
-    });
-
-    results
-        .into_iter()
-        .map(|f| match f {
-            Some(res) => {
-                res.join();
-            }
-            _ => (),
-        })
-        .collect::<Vec<_>>();
-    loop_result.join();
+    }
 }
 
 fn main() {